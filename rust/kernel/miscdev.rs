@@ -2,12 +2,17 @@
 
 //! Miscellaneous devices.
 //!
-use core::{ffi::c_void, marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+use core::{
+    cell::UnsafeCell, ffi::c_void, marker::PhantomPinned, mem::MaybeUninit, pin::Pin,
+};
 
-use crate::{c_str, pr_info};
+use crate::pr_info;
 use alloc::{boxed::Box, vec::Vec};
 use kernel::{
-    bindings::{file, inode, misc_deregister, misc_register, miscdevice, MISC_DYNAMIC_MINOR},
+    bindings::{
+        cond_resched, file, inode, init_waitqueue_head, misc_deregister, misc_register,
+        miscdevice, poll_table, wait_queue_head_t, wake_up_interruptible, MISC_DYNAMIC_MINOR,
+    },
     prelude::*,
     types::ForeignOwnable,
 };
@@ -28,7 +33,8 @@ use kernel::{
 ///
 /// impl kernel::Module for RustCltModule {
 ///     fn init(_module: &'static ThisModule) -> Result<Self> {
-///         let registration = miscdev::Registration::new_pinned_registered(())?;
+///         let registration =
+///             miscdev::Registration::new_pinned_registered(c_str!("my_miscdev"), None, ())?;
 ///         Ok(MyMiscDevice {
 ///             _registration: registration,
 ///         })
@@ -70,9 +76,9 @@ where
         release: Some(Self::release_callback),
         read: Some(Self::read_callback),
         write: Some(Self::write_callback),
-        llseek: Some(kernel::bindings::noop_llseek),
+        llseek: Some(Self::llseek_callback),
         check_flags: None,
-        compat_ioctl: None,
+        compat_ioctl: Some(Self::compat_ioctl_callback),
         copy_file_range: None,
         fallocate: None,
         fadvise: None,
@@ -87,7 +93,7 @@ where
         mmap: None,
         mmap_supported_flags: 0,
         owner: core::ptr::null_mut(),
-        poll: None,
+        poll: Some(Self::poll_callback),
         read_iter: None,
         remap_file_range: None,
         setlease: None,
@@ -95,24 +101,36 @@ where
         splice_read: None,
         splice_eof: None,
         splice_write: None,
-        unlocked_ioctl: None,
+        unlocked_ioctl: Some(Self::unlocked_ioctl_callback),
         uring_cmd: None,
         uring_cmd_iopoll: None,
         write_iter: None,
     };
 
-    /// Register the device on the kernel. When the device file is open, supply `T::open` with `data`.
-    pub fn new_pinned_registered(data: T::OpenData) -> Result<Pin<Box<Self>>> {
+    /// Maximum number of bytes staged in a kernel-side buffer per [`MiscDev::read`] call. Larger `read(2)` requests are served in several chunks of at most this size instead of allocating a single buffer sized to the (untrusted) requested count.
+    const READ_CHUNK_SIZE: usize = 4096;
+
+    /// Register the device on the kernel under `/dev/<name>`. When the device file is open, supply `T::open` with `data`. `minor` requests a fixed minor number; pass `None` to let the kernel pick one dynamically (`MISC_DYNAMIC_MINOR`), which is what most drivers want. A module may call this several times with distinct `name`s to expose multiple independent `/dev` nodes, each backed by its own `Registration`.
+    pub fn new_pinned_registered(
+        name: &'static CStr,
+        minor: Option<i32>,
+        data: T::OpenData,
+    ) -> Result<Pin<Box<Self>>> {
         let registration = Registration::default();
         let registration = Box::try_new(registration)?;
         pr_info!("Registration place at {:p}", &registration);
         let mut registration = Pin::from(registration);
-        Self::register(registration.as_mut(), data)?;
+        Self::register(registration.as_mut(), name, minor, data)?;
         Ok(registration)
     }
 
     /// Register the device on the kernel with an already pinned data
-    fn register(self: Pin<&mut Self>, data: T::OpenData) -> Result<()> {
+    fn register(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        minor: Option<i32>,
+        data: T::OpenData,
+    ) -> Result<()> {
         let registration = unsafe { self.get_unchecked_mut() };
         if registration.registered {
             // Already registered.
@@ -120,8 +138,8 @@ where
         }
 
         // Prepare kernel structure for misc device, ref [`chrdev.c`](chrdev.c)
-        registration.miscdev.minor = MISC_DYNAMIC_MINOR as i32;
-        registration.miscdev.name = c_str!("rchrdev").as_char_ptr();
+        registration.miscdev.minor = minor.unwrap_or(MISC_DYNAMIC_MINOR as i32);
+        registration.miscdev.name = name.as_char_ptr();
         registration.miscdev.fops = &Self::FOPS;
         registration.registered = true;
         registration.open_data.write(data);
@@ -139,7 +157,7 @@ where
             registration.open_data.as_ptr()
         );
 
-        pr_info!("Registered a new misc device `rchrdev`\n");
+        pr_info!("Registered a new misc device `{}`\n", name);
         Ok(())
     }
 
@@ -180,30 +198,80 @@ where
         pr_info!("file pointer private data at {:p}\n", unsafe {
             (*filp).private_data
         });
-        // Borrow data from kernel of type `Data`
-        let data = unsafe { <T as MiscDev>::Data::borrow((*filp).private_data) };
-        pr_info!("Data for misc device placed at {:p}", &data);
-        let device_buf = match T::read(data, count, unsafe { *ppos } as isize) {
-            Ok(rlen) => rlen,
-            Err(err) => return -(err.to_errno() as isize),
-        };
-        let device_buf_len = device_buf.len() as u64;
-        // Copy kernel data from kernel to user space
-        let res = unsafe {
-            kernel::bindings::_copy_to_user(
-                buffer as *mut c_void,
-                device_buf.as_ptr() as *const c_void,
-                device_buf_len,
-            )
-        };
-        if res == 0 {
-            pr_info!("Read_response has {device_buf_len} bytes\n");
-            unsafe { *filp }.f_pos += device_buf_len as i64;
-            device_buf_len as isize
+
+        // Bounded kernel-side staging buffer, reused across chunks so a single large
+        // `read(2)` request doesn't force an allocation sized to the untrusted `count`.
+        let chunk_len = core::cmp::min(count, Self::READ_CHUNK_SIZE);
+        let mut device_buf = if let Ok(buf) = Vec::try_with_capacity(chunk_len) {
+            buf
         } else {
-            pr_err!("Problem while copying data to user space: {res}");
-            -(EINVAL.to_errno() as isize)
+            pr_err!("Can't allocate {chunk_len} bytes\n");
+            return -(EFAULT.to_errno() as isize);
+        };
+        if device_buf.try_resize(chunk_len, 0u8).is_err() {
+            pr_err!("Can't resize vector to {chunk_len} elements\n");
+            return -(EFAULT.to_errno() as isize);
         }
+
+        let mut pos = unsafe { *ppos };
+        let mut total = 0usize;
+        while total < count {
+            let want = core::cmp::min(count - total, chunk_len);
+            // Borrow data from kernel of type `Data`
+            let data = unsafe { <T as MiscDev>::Data::borrow((*filp).private_data) };
+            let rlen = match T::read(data, &mut device_buf[..want], pos as isize) {
+                Ok(rlen) => rlen,
+                Err(err) => {
+                    if total > 0 {
+                        // Already copied some data to userspace; report the short read
+                        // instead of discarding it for an error on the next chunk.
+                        break;
+                    }
+                    return -(err.to_errno() as isize);
+                }
+            };
+            if rlen > want {
+                pr_err!("MiscDev::read reported {rlen} bytes, but only {want} were requested\n");
+                return -(EFAULT.to_errno() as isize);
+            }
+            if rlen == 0 {
+                // Nothing left to read at this position.
+                break;
+            }
+
+            // Copy kernel data from kernel to user space
+            let res = unsafe {
+                kernel::bindings::_copy_to_user(
+                    buffer.add(total) as *mut c_void,
+                    device_buf.as_ptr() as *const c_void,
+                    rlen as u64,
+                )
+            };
+            if res != 0 {
+                pr_err!("Problem while copying data to user space: {res}");
+                if total > 0 {
+                    break;
+                }
+                return -(EINVAL.to_errno() as isize);
+            }
+
+            pos += rlen as i64;
+            total += rlen;
+            if rlen < want {
+                // Short chunk; treat as EOF rather than looping for more.
+                break;
+            }
+
+            // A single large `read(2)` against a device that always fills its chunk
+            // would otherwise loop here without ever yielding the CPU; give the
+            // scheduler a chance between chunks to avoid a soft-lockup/RCU stall.
+            unsafe { cond_resched() };
+        }
+
+        pr_info!("Read_response has {total} bytes\n");
+        unsafe { *ppos = pos };
+        unsafe { *filp }.f_pos = pos;
+        total as isize
     }
 
     /// Unsafe wrapper to unpack kernel structures into safe rust world
@@ -252,6 +320,71 @@ where
         }
     }
 
+    /// Unsafe wrapper to unpack kernel structures into safe rust world
+    unsafe extern "C" fn llseek_callback(
+        filp: *mut file,
+        offset: kernel::bindings::loff_t,
+        whence: core::ffi::c_int,
+    ) -> kernel::bindings::loff_t {
+        pr_info!("Called llseek_callback\n");
+        // Borrow data from kernel of type `Data`
+        let data = unsafe { <T as MiscDev>::Data::borrow((*filp).private_data) };
+        let whence = match SeekWhence::try_from(whence) {
+            Ok(whence) => whence,
+            Err(_) => return -(EINVAL.to_errno() as kernel::bindings::loff_t),
+        };
+        let pos = unsafe { (*filp).f_pos };
+        match T::seek(data, pos, offset, whence) {
+            Ok(new_pos) if new_pos >= 0 => {
+                unsafe { (*filp).f_pos = new_pos };
+                new_pos
+            }
+            Ok(_) => -(EINVAL.to_errno() as kernel::bindings::loff_t),
+            Err(err) => -(err.to_errno() as kernel::bindings::loff_t),
+        }
+    }
+
+    /// Unsafe wrapper to unpack kernel structures into safe rust world
+    unsafe extern "C" fn poll_callback(
+        filp: *mut file,
+        table: *mut poll_table,
+    ) -> kernel::bindings::__poll_t {
+        pr_info!("Called poll_callback\n");
+        // Borrow data from kernel of type `Data`
+        let data = unsafe { <T as MiscDev>::Data::borrow((*filp).private_data) };
+        T::poll(data, filp, table) as kernel::bindings::__poll_t
+    }
+
+    /// Unsafe wrapper to unpack kernel structures into safe rust world
+    unsafe extern "C" fn unlocked_ioctl_callback(
+        filp: *mut file,
+        cmd: core::ffi::c_uint,
+        arg: core::ffi::c_ulong,
+    ) -> core::ffi::c_long {
+        pr_info!("Called unlocked_ioctl_callback\n");
+        // Borrow data from kernel of type `Data`
+        let data = unsafe { <T as MiscDev>::Data::borrow((*filp).private_data) };
+        match T::ioctl(data, cmd, arg as usize) {
+            Ok(ret) => ret as core::ffi::c_long,
+            Err(err) => -(err.to_errno() as core::ffi::c_long),
+        }
+    }
+
+    /// Unsafe wrapper to unpack kernel structures into safe rust world
+    ///
+    /// Dispatched for 32-bit ioctl callers on a 64-bit kernel. This crate does not
+    /// translate any argument layout, so it is only a plain pass-through to
+    /// [`Self::unlocked_ioctl_callback`]; drivers whose `cmd`s carry pointers or
+    /// otherwise differ in size between ABIs must handle that themselves in `T::ioctl`.
+    unsafe extern "C" fn compat_ioctl_callback(
+        filp: *mut file,
+        cmd: core::ffi::c_uint,
+        arg: core::ffi::c_ulong,
+    ) -> core::ffi::c_long {
+        pr_info!("Called compat_ioctl_callback\n");
+        unsafe { Self::unlocked_ioctl_callback(filp, cmd, arg) }
+    }
+
     /// Unsafe wrapper to unpack kernel structures into safe rust world
     unsafe extern "C" fn release_callback(_inode: *mut inode, filp: *mut file) -> core::ffi::c_int {
         pr_info!("Called release_callback\n");
@@ -294,21 +427,19 @@ impl<T: MiscDev> Drop for Registration<T> {
 ///         Ok(Arc::try_new(AtomicUsize::new(0))?)
 ///     }
 ///
-///     fn read(context: ArcBorrow<'_, AtomicUsize>, count: usize, _ppos: isize) -> Result<Vec<u8>> {
+///     fn read(context: ArcBorrow<'_, AtomicUsize>, buf: &mut [u8], _pos: isize) -> Result<usize> {
 ///         // Get head position
 ///         let head = context.load(Ordering::Relaxed);
 ///         // Determine the head position after read
-///         let to = core::cmp::min(head + count, READ_DATA.len());
+///         let to = core::cmp::min(head + buf.len(), READ_DATA.len());
+///         let len = to - head;
 ///
 ///         // Fill the read buffer
-///         let mut buf = Vec::new();
-///         for i in READ_DATA[head..to].as_bytes() {
-///             buf.try_push(*i)?;
-///         }
+///         buf[..len].copy_from_slice(READ_DATA[head..to].as_bytes());
 ///
 ///         // Update the head position
 ///         context.store(to, Ordering::Relaxed);
-///         Ok(buf)
+///         Ok(len)
 ///     }
 /// }
 /// ```
@@ -323,12 +454,12 @@ pub trait MiscDev {
         Err(EINVAL)
     }
 
-    /// A read operation was called for the device file. `Data` will be borrowed from the Kernel owned data structure. `count` represents the requested bytes. `_pos` is the current position in the file. A buffer is returned.
+    /// A read operation was called for the device file. `Data` will be borrowed from the Kernel owned data structure. `buf` is a kernel-side buffer sized to the caller's requested byte count, capped at `READ_CHUNK_SIZE` bytes, to be filled starting at its beginning; `_pos` is the current position in the file. Returns the number of bytes actually written into `buf` (0 at EOF), which is exactly how many bytes get copied to user space and how far `_pos` is advanced. A larger `read(2)` request calls this several times with an advancing `_pos`, until it returns fewer bytes than `buf` holds.
     fn read(
         _context: <Self::Data as ForeignOwnable>::Borrowed<'_>,
-        _count: usize,
+        _buf: &mut [u8],
         _pos: isize,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<usize> {
         Err(EINVAL)
     }
 
@@ -345,6 +476,217 @@ pub trait MiscDev {
     fn release(_context: Self::Data) -> Result<()> {
         Err(EINVAL)
     }
+
+    /// An `ioctl(2)` call was made on the device file. `Data` will be borrowed from the Kernel owned data structure. `cmd` is the (driver-defined) command number, typically built with the helpers in [`ioctl`], and `arg` is the caller-supplied argument, often a user space pointer. The result is passed back to userspace as the ioctl return value.
+    fn ioctl(
+        _context: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        _cmd: u32,
+        _arg: usize,
+    ) -> Result<isize> {
+        Err(EINVAL)
+    }
+
+    /// A `poll(2)`/`epoll(7)` readiness check was made on the device file. `Data` will be borrowed from the Kernel owned data structure. Implementers that can block should call [`WaitQueue::poll_wait`] with `filp` and `table` on a queue kept in `Data`, then return a bitmask of the readiness flags (e.g. `bindings::POLLIN`, `bindings::POLLOUT`) that currently apply. The default implementation reports the device as always ready for both reading and writing.
+    fn poll(
+        _context: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        _filp: *mut file,
+        _table: *mut poll_table,
+    ) -> u32 {
+        kernel::bindings::POLLIN | kernel::bindings::POLLOUT
+    }
+
+    /// A `llseek(2)` call was made on the device file. `Data` will be borrowed from the Kernel owned data structure. `pos` is the current position, `offset` and `whence` describe the requested new position relative to `whence`. Returns the new absolute position. Drivers backed by a single fixed-size buffer can delegate to [`seek_fixed_size`] instead of reimplementing this. The default implementation rejects seeking.
+    fn seek(
+        _context: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        _pos: i64,
+        _offset: i64,
+        _whence: SeekWhence,
+    ) -> Result<i64> {
+        Err(EINVAL)
+    }
+}
+
+/// Reference point for a `llseek(2)` request, mirroring the kernel's `SEEK_*` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekWhence {
+    /// Seek to an absolute position (`SEEK_SET`).
+    Set,
+    /// Seek relative to the current position (`SEEK_CUR`).
+    Cur,
+    /// Seek relative to the end of the data (`SEEK_END`).
+    End,
+}
+
+impl core::convert::TryFrom<core::ffi::c_int> for SeekWhence {
+    type Error = Error;
+
+    fn try_from(whence: core::ffi::c_int) -> Result<Self> {
+        match whence as u32 {
+            kernel::bindings::SEEK_SET => Ok(SeekWhence::Set),
+            kernel::bindings::SEEK_CUR => Ok(SeekWhence::Cur),
+            kernel::bindings::SEEK_END => Ok(SeekWhence::End),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// Common `MiscDev::seek` behaviour for devices backed by a single fixed-size buffer of
+/// `size` bytes: `SEEK_SET`/`SEEK_CUR`/`SEEK_END` are resolved against it and negative
+/// resulting positions are rejected, matching what most simple misc devices want.
+pub fn seek_fixed_size(pos: i64, offset: i64, whence: SeekWhence, size: usize) -> Result<i64> {
+    let new_pos = match whence {
+        SeekWhence::Set => Some(offset),
+        SeekWhence::Cur => pos.checked_add(offset),
+        SeekWhence::End => (size as i64).checked_add(offset),
+    };
+    match new_pos {
+        Some(new_pos) if new_pos >= 0 => Ok(new_pos),
+        _ => Err(EINVAL),
+    }
+}
+
+/// A wait queue a [`MiscDev`] implementation can keep in its `Data` to block readers or
+/// writers until another file handle makes progress, mirroring the kernel's
+/// `wait_queue_head_t`.
+///
+/// [`Self::init`] runs `INIT_LIST_HEAD` on the embedded queue, which makes it
+/// self-referential; the queue must therefore never move again afterwards, which is
+/// why `init` takes `Pin<&Self>`. Construct it in place inside the allocation `Data`
+/// already lives behind (its `Arc`/`Box`) and initialise it there, before sharing `Data`
+/// any further.
+///
+/// ```rust,no_run
+/// # use kernel::prelude::*;
+/// # use kernel::miscdev::WaitQueue;
+/// # use core::pin::Pin;
+/// struct Data {
+///     queue: WaitQueue,
+/// }
+///
+/// let data = Arc::try_new(Data {
+///     queue: WaitQueue::new(),
+/// })?;
+/// // SAFETY: `data`'s contents live behind the `Arc`'s heap allocation and will never
+/// // be moved again.
+/// unsafe { Pin::new_unchecked(&data.queue) }.init();
+/// # Ok::<(), Error>(())
+/// ```
+pub struct WaitQueue {
+    wq: UnsafeCell<wait_queue_head_t>,
+    // `init` makes the queue self-referential; it must not move afterwards.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `wait_queue_head_t` is safe to access from multiple threads under its own
+// internal locking, same as in the C kernel.
+unsafe impl Send for WaitQueue {}
+// SAFETY: see above.
+unsafe impl Sync for WaitQueue {}
+
+impl WaitQueue {
+    /// Creates a new, uninitialised wait queue. [`Self::init`] must be called once, through a pinned reference, before any other method is used.
+    pub fn new() -> Self {
+        WaitQueue {
+            // SAFETY: a zeroed `wait_queue_head_t` is a valid bit pattern for the type;
+            // it just isn't initialised as a wait queue yet.
+            wq: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the wait queue in place. Must be called exactly once, e.g. from `MiscDev::open` right after allocating `Data`, before the queue is used. `self` being pinned is the caller's promise that the queue will not move again, since this creates self-referential pointers into it.
+    pub fn init(self: Pin<&Self>) {
+        unsafe { init_waitqueue_head(self.wq.get()) };
+    }
+
+    /// Registers the current `poll(2)`/`epoll(7)` caller on this queue, so that it is woken up the next time [`Self::wake_up`] is called. Intended to be called from [`MiscDev::poll`] with the `filp` and `table` handed to it.
+    pub fn poll_wait(self: Pin<&Self>, filp: *mut file, table: *mut poll_table) {
+        unsafe { kernel::bindings::poll_wait(filp, self.wq.get(), table) };
+    }
+
+    /// Wakes up all tasks that are waiting interruptibly on this queue, e.g. after `MiscDev::write` produced new data for blocked readers.
+    pub fn wake_up(self: Pin<&Self>) {
+        unsafe { wake_up_interruptible(self.wq.get()) };
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Helpers for encoding and decoding `ioctl(2)` command numbers.
+///
+/// Mirrors the layout from the kernel's `include/uapi/asm-generic/ioctl.h`: a command
+/// number packs a direction, a one-byte "type" (the driver's magic number), an 8-bit
+/// sequence number and the size of the argument being transferred.
+#[allow(non_snake_case)]
+pub mod ioctl {
+    const NRBITS: u32 = 8;
+    const TYPEBITS: u32 = 8;
+    const SIZEBITS: u32 = 14;
+
+    const NRSHIFT: u32 = 0;
+    const TYPESHIFT: u32 = NRSHIFT + NRBITS;
+    const SIZESHIFT: u32 = TYPESHIFT + TYPEBITS;
+    const DIRSHIFT: u32 = SIZESHIFT + SIZEBITS;
+
+    const NRMASK: u32 = (1 << NRBITS) - 1;
+    const TYPEMASK: u32 = (1 << TYPEBITS) - 1;
+    const SIZEMASK: u32 = (1 << SIZEBITS) - 1;
+
+    /// No data is transferred between user and kernel space.
+    pub const NONE: u32 = 0;
+    /// Userspace is writing data that the driver will read.
+    pub const WRITE: u32 = 1;
+    /// The driver is writing data that userspace will read.
+    pub const READ: u32 = 2;
+
+    /// Encodes an `ioctl(2)` command number from its direction, type, number and argument size.
+    pub const fn _IOC(dir: u32, ty: u32, nr: u32, size: u32) -> u32 {
+        (dir << DIRSHIFT) | (ty << TYPESHIFT) | (nr << NRSHIFT) | (size << SIZESHIFT)
+    }
+
+    /// Encodes a command number that transfers no argument data.
+    pub const fn _IO(ty: u32, nr: u32) -> u32 {
+        _IOC(NONE, ty, nr, 0)
+    }
+
+    /// Encodes a command number through which the driver writes `size` bytes back to userspace.
+    pub const fn _IOR(ty: u32, nr: u32, size: u32) -> u32 {
+        _IOC(READ, ty, nr, size)
+    }
+
+    /// Encodes a command number through which userspace writes `size` bytes to the driver.
+    pub const fn _IOW(ty: u32, nr: u32, size: u32) -> u32 {
+        _IOC(WRITE, ty, nr, size)
+    }
+
+    /// Encodes a command number that both writes and reads `size` bytes.
+    pub const fn _IOWR(ty: u32, nr: u32, size: u32) -> u32 {
+        _IOC(READ | WRITE, ty, nr, size)
+    }
+
+    /// Extracts the direction ([`NONE`]/[`WRITE`]/[`READ`]) from a command number.
+    pub const fn _IOC_DIR(cmd: u32) -> u32 {
+        (cmd >> DIRSHIFT) & ((1 << 2) - 1)
+    }
+
+    /// Extracts the type (the driver's magic number) from a command number.
+    pub const fn _IOC_TYPE(cmd: u32) -> u32 {
+        (cmd >> TYPESHIFT) & TYPEMASK
+    }
+
+    /// Extracts the sequence number from a command number.
+    pub const fn _IOC_NR(cmd: u32) -> u32 {
+        (cmd >> NRSHIFT) & NRMASK
+    }
+
+    /// Extracts the argument size from a command number.
+    pub const fn _IOC_SIZE(cmd: u32) -> u32 {
+        (cmd >> SIZESHIFT) & SIZEMASK
+    }
 }
 
 /// Calculates the offset of a field from the beginning of the struct it belongs to.
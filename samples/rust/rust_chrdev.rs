@@ -1,10 +1,14 @@
 // SPDX-License-Identifier: GPL-2.0
 
 //! Rust character device sample.
-use core::pin::Pin;
+use core::{
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use alloc::boxed::Box;
-use kernel::miscdev;
+use kernel::bindings::{file, poll_table};
+use kernel::miscdev::{self, WaitQueue};
 use kernel::prelude::*;
 
 module! {
@@ -15,36 +19,71 @@ module! {
     license: "GPL",
 }
 
+/// Fixed response handed out by [`Callback::read`] once it has been made ready by a write.
+const RESPONSE: &[u8] = b"Hello CLT!";
+
+/// Per-open state: the canned response only becomes readable after the first write, and
+/// `queue` lets `poll(2)`/`epoll(7)` callers wait for that instead of busy-polling.
+/// `read` itself does not block: it reports end of file until the first write.
+struct Data {
+    queue: WaitQueue,
+    ready: AtomicBool,
+}
+
 struct Callback {}
 
 impl miscdev::MiscDev for Callback {
-    type Data = ();
+    type Data = Arc<Data>;
     type OpenData = ();
 
-    fn open(open_data: &()) -> Result<()> {
-        pr_info!("Open data located at {:p}", open_data);
-        Ok(open_data.clone())
+    fn open(_open_data: &()) -> Result<Self::Data> {
+        let data = Arc::try_new(Data {
+            queue: WaitQueue::new(),
+            ready: AtomicBool::new(false),
+        })?;
+        // SAFETY: `data`'s contents live behind the `Arc`'s heap allocation and will
+        // never be moved again.
+        unsafe { Pin::new_unchecked(&data.queue) }.init();
+        Ok(data)
+    }
+
+    fn read(context: ArcBorrow<'_, Data>, buf: &mut [u8], pos: isize) -> Result<usize> {
+        pr_info!("Got read request for {} bytes from position {pos}", buf.len());
+        if !context.ready.load(Ordering::Acquire) || pos < 0 || pos as usize >= RESPONSE.len() {
+            return Ok(0);
+        }
+        let data = &RESPONSE[pos as usize..];
+        let len = core::cmp::min(buf.len(), data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn write(context: ArcBorrow<'_, Data>, data: &[u8], pos: isize) -> Result<isize> {
+        pr_info!("Got write request for {data:?} bytes from position {pos} -> marking the response ready\n");
+        context.ready.store(true, Ordering::Release);
+        // SAFETY: the queue was pinned and initialised in `open`, and `Data` (and
+        // therefore the queue) is never moved again afterwards.
+        unsafe { Pin::new_unchecked(&context.queue) }.wake_up();
+        Ok(data.len() as isize)
     }
 
-    fn read(context: (), count: usize, ppos: isize) -> Result<Vec<u8>> {
-        pr_info!("Context data points to {:p}", &context);
-        pr_info!("Got read request for {count} bytes from position {ppos}");
-        let mut res = Vec::new();
-        for i in "Hello CLT!".bytes() {
-            res.try_push(i)?;
+    fn poll(context: ArcBorrow<'_, Data>, filp: *mut file, table: *mut poll_table) -> u32 {
+        // SAFETY: see `write`.
+        unsafe { Pin::new_unchecked(&context.queue) }.poll_wait(filp, table);
+        if context.ready.load(Ordering::Acquire) {
+            kernel::bindings::POLLIN
+        } else {
+            0
         }
-        pr_info!(
-            "OMG! I do not have a persitent state yet! Will give you the same response FOREVER!"
-        );
-        Ok(res)
     }
 
-    fn write(context: (), data: &[u8], pos: isize) -> Result<isize> {
-        pr_info!("Context data points to {:p}", &context);
-        pr_info!(
-            "Got write request for {data:?} bytes from position {pos} -> Nope not doing it! Yet.."
-        );
-        Err(EINVAL)
+    fn seek(
+        _context: ArcBorrow<'_, Data>,
+        pos: i64,
+        offset: i64,
+        whence: miscdev::SeekWhence,
+    ) -> Result<i64> {
+        miscdev::seek_fixed_size(pos, offset, whence, RESPONSE.len())
     }
 }
 
@@ -58,7 +97,8 @@ impl kernel::Module for RustChrdev {
         pr_info!("Rust device driver init\n");
         pr_info!("*module = {:p}\n", _module);
         let state = ();
-        let registration = miscdev::Registration::new_pinned_registered(state)?;
+        let registration =
+            miscdev::Registration::new_pinned_registered(c_str!("rust_chrdev"), None, state)?;
         Ok(RustChrdev {
             _registration: registration,
         })